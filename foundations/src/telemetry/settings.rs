@@ -0,0 +1,210 @@
+//! Tracing configuration.
+
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Settings for the distributed tracing harness.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TracingSettings {
+    /// Enables distributed tracing.
+    pub enabled: bool,
+
+    /// The sampling strategy applied when a root span is started.
+    pub sampling_strategy: SamplingStrategy,
+
+    /// Where finished spans are reported to.
+    pub output: TracesOutput,
+
+    /// When set, buffers each trace until its root span completes and applies a
+    /// keep/drop decision based on the trace's outcome before forwarding it to `output`.
+    pub tail_sampling: Option<TailSamplingSettings>,
+}
+
+impl Default for TracingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sampling_strategy: SamplingStrategy::default(),
+            output: TracesOutput::default(),
+            tail_sampling: None,
+        }
+    }
+}
+
+/// Picks whether and how often root spans are sampled before their outcome is known.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum SamplingStrategy {
+    /// Samples every span that's explicitly marked as sampled by an upstream caller,
+    /// but never originates a new sampling decision itself.
+    Passive,
+    /// Originates sampling decisions locally, rate-limited to a maximum number of
+    /// sampled traces per second.
+    Active(ActiveSamplingSettings),
+}
+
+impl Default for SamplingStrategy {
+    fn default() -> Self {
+        Self::Active(ActiveSamplingSettings::default())
+    }
+}
+
+/// Settings for [`SamplingStrategy::Active`]'s rate-limiting probabilistic sampler.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActiveSamplingSettings {
+    /// The maximum number of traces sampled per second.
+    pub max_traces_per_second: f64,
+}
+
+impl Default for ActiveSamplingSettings {
+    fn default() -> Self {
+        Self {
+            max_traces_per_second: 1.0,
+        }
+    }
+}
+
+/// Where the reporter task sends finished spans.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum TracesOutput {
+    /// Reports spans to a Jaeger agent over UDP, using the Thrift compact protocol.
+    JaegerThriftUdp(JaegerThriftUdpOutputSettings),
+
+    /// Reports spans to an OTLP-compatible collector over gRPC.
+    #[cfg(feature = "telemetry-otlp-grpc")]
+    OpenTelemetryGrpc(OpenTelemetryGrpcOutputSettings),
+
+    /// Reports spans as Zipkin v2 JSON, POSTed to a collector's HTTP endpoint.
+    Zipkin(ZipkinOutputSettings),
+
+    /// Writes spans as newline-delimited JSON to a rotating file on disk.
+    File(FileOutputSettings),
+}
+
+impl Default for TracesOutput {
+    fn default() -> Self {
+        Self::JaegerThriftUdp(JaegerThriftUdpOutputSettings::default())
+    }
+}
+
+/// Settings for [`TracesOutput::JaegerThriftUdp`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JaegerThriftUdpOutputSettings {
+    /// Address of the local Jaeger agent's compact-thrift UDP listener.
+    pub agent_addr: SocketAddr,
+}
+
+impl Default for JaegerThriftUdpOutputSettings {
+    fn default() -> Self {
+        Self {
+            agent_addr: SocketAddr::from(([127, 0, 0, 1], 6831)),
+        }
+    }
+}
+
+/// Settings for [`TracesOutput::OpenTelemetryGrpc`].
+#[cfg(feature = "telemetry-otlp-grpc")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenTelemetryGrpcOutputSettings {
+    /// The OTLP/gRPC collector endpoint, e.g. `http://localhost:4317`.
+    pub endpoint: String,
+}
+
+#[cfg(feature = "telemetry-otlp-grpc")]
+impl Default for OpenTelemetryGrpcOutputSettings {
+    fn default() -> Self {
+        Self {
+            endpoint: "http://localhost:4317".to_owned(),
+        }
+    }
+}
+
+/// Settings for [`TracesOutput::Zipkin`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZipkinOutputSettings {
+    /// The Zipkin v2 HTTP collector endpoint spans are POSTed to, e.g.
+    /// `http://localhost:9411/api/v2/spans`.
+    pub collector_url: String,
+
+    /// Optional IPv4 address of this service, attached to each span's `localEndpoint`.
+    pub local_ipv4: Option<Ipv4Addr>,
+
+    /// Optional port of this service, attached to each span's `localEndpoint`.
+    pub local_port: Option<u16>,
+}
+
+impl Default for ZipkinOutputSettings {
+    fn default() -> Self {
+        Self {
+            collector_url: "http://localhost:9411/api/v2/spans".to_owned(),
+            local_ipv4: None,
+            local_port: None,
+        }
+    }
+}
+
+/// Settings for [`TracesOutput::File`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileOutputSettings {
+    /// Directory the rotated trace files are written to.
+    pub directory: PathBuf,
+
+    /// Prefix prepended to the date component of each rotated file's name. Skipped,
+    /// along with its separating dot, when empty.
+    pub filename_prefix: String,
+
+    /// Suffix appended to the date component of each rotated file's name, e.g.
+    /// `"jsonl"`. Skipped, along with its separating dot, when empty.
+    pub filename_suffix: String,
+
+    /// Spans are rotated into a new file once the current one reaches this size, in
+    /// addition to the hourly time-based rotation.
+    pub max_file_size_bytes: u64,
+}
+
+impl Default for FileOutputSettings {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("."),
+            filename_prefix: "traces".to_owned(),
+            filename_suffix: "jsonl".to_owned(),
+            max_file_size_bytes: 100 * 1024 * 1024,
+        }
+    }
+}
+
+/// Settings for the tail-sampling stage (see [`TracingSettings::tail_sampling`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TailSamplingSettings {
+    /// A trace is always kept if its root span's duration is at least this long.
+    pub latency_threshold: Duration,
+
+    /// Probability, in `[0.0, 1.0]`, that a trace which is neither errored nor slow is
+    /// kept anyway.
+    pub sample_probability: f64,
+
+    /// Maximum number of traces buffered while waiting for their root span to
+    /// complete. Bounds memory when roots never complete (e.g. a flood of
+    /// disconnecting clients) by evicting the oldest incomplete trace first.
+    pub max_buffered_traces: usize,
+}
+
+impl Default for TailSamplingSettings {
+    fn default() -> Self {
+        Self {
+            latency_threshold: Duration::from_secs(1),
+            sample_probability: 0.1,
+            max_buffered_traces: 10_000,
+        }
+    }
+}
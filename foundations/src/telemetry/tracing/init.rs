@@ -1,32 +1,69 @@
 use super::internal::{SharedSpan, Tracer};
 use super::live_reference_set::LiveReferenceSet;
+use super::output_file;
 use super::output_jaeger_thrift_udp;
+use super::output_zipkin;
+use super::tail_sampling;
 use crate::telemetry::scope::ScopeStack;
 use crate::telemetry::settings::{SamplingStrategy, TracesOutput, TracingSettings};
 use crate::telemetry::tracing::event_output::spans_to_trace_events;
 use crate::{BootstrapResult, ServiceInfo};
 use cf_rustracing_jaeger::span::{Span, SpanReceiver};
-use futures_util::future::BoxFuture;
 use once_cell::sync::{Lazy, OnceCell};
 use std::sync::Arc;
 use std::time::SystemTime;
+use tokio::sync::{mpsc as control_mpsc, oneshot, Mutex as AsyncMutex};
+use tokio::task::JoinHandle;
 
 #[cfg(feature = "telemetry-otlp-grpc")]
 use super::output_otlp_grpc;
 
-use cf_rustracing::sampler::{PassiveSampler, Sampler};
+use cf_rustracing::sampler::{BoxSampler, PassiveSampler, Sampler};
+use cf_rustracing::span::StartSpanOptions;
+use cf_rustracing_jaeger::span::SpanContextState;
 #[cfg(feature = "testing")]
 use std::borrow::Cow;
 
 use crate::telemetry::tracing::rate_limit::RateLimitingProbabilisticSampler;
 
+/// A [`Sampler`] that can be swapped out live, so [`TracingHarness::set_sampling_strategy`]
+/// can ramp `SamplingStrategy` up or down (e.g. during an incident) without rebuilding the
+/// `Tracer`, restarting the reporter task, or touching the span channel.
+#[derive(Clone)]
+struct ReloadableSampler(Arc<parking_lot::Mutex<BoxSampler<SpanContextState>>>);
+
+impl ReloadableSampler {
+    fn new(sampler: BoxSampler<SpanContextState>) -> Self {
+        Self(Arc::new(parking_lot::Mutex::new(sampler)))
+    }
+
+    fn swap(&self, sampler: BoxSampler<SpanContextState>) {
+        *self.0.lock() = sampler;
+    }
+}
+
+impl Sampler<SpanContextState> for ReloadableSampler {
+    fn is_sampled(&mut self, span: &StartSpanOptions<SpanContextState>) -> bool {
+        self.0.lock().is_sampled(span)
+    }
+}
+
+fn sampler_for_strategy(strategy: &SamplingStrategy) -> BootstrapResult<BoxSampler<SpanContextState>> {
+    Ok(match strategy {
+        SamplingStrategy::Passive => PassiveSampler.boxed(),
+        SamplingStrategy::Active(settings) => RateLimitingProbabilisticSampler::new(settings)?.boxed(),
+    })
+}
+
 static HARNESS: OnceCell<TracingHarness> = OnceCell::new();
 
 static NOOP_HARNESS: Lazy<TracingHarness> = Lazy::new(|| {
-    let (noop_tracer, _) = Tracer::new(RateLimitingProbabilisticSampler::default().boxed());
+    let sampler = ReloadableSampler::new(RateLimitingProbabilisticSampler::default().boxed());
+    let (noop_tracer, _) = Tracer::new(sampler.clone().boxed());
 
     TracingHarness {
         tracer: noop_tracer,
+        sampler,
         span_scope_stack: Default::default(),
 
         #[cfg(feature = "testing")]
@@ -34,11 +71,35 @@ static NOOP_HARNESS: Lazy<TracingHarness> = Lazy::new(|| {
 
         active_roots: Default::default(),
         tracing_start: SystemTime::now(),
+        reporter: None,
     }
 });
 
+/// Requests sent to the reporter task started by [`init`], mirroring the
+/// force-flush/shutdown split exposed by `opentelemetry`'s tracer provider.
+///
+/// `pub(crate)` so that the `output_*` reporter backends can react to it.
+pub(crate) enum ReporterControl {
+    /// Flush any spans currently buffered, acking once the in-flight batch has been sent.
+    Flush(oneshot::Sender<()>),
+    /// Stop accepting new spans, flush what's buffered, and ack once drained.
+    Shutdown(oneshot::Sender<()>),
+}
+
+/// Receiving half of the [`ReporterControl`] channel, passed to each `output_*` backend's
+/// `start` function alongside the `SpanReceiver`.
+pub(crate) type ReporterControlReceiver = control_mpsc::UnboundedReceiver<ReporterControl>;
+
+/// Handle used by [`TracingHarness`] to drive the reporter task's lifecycle from outside
+/// of it, by sending [`ReporterControl`] requests and joining the task on shutdown.
+struct ReporterHandle {
+    control_tx: control_mpsc::UnboundedSender<ReporterControl>,
+    join_handle: AsyncMutex<Option<JoinHandle<BootstrapResult<()>>>>,
+}
+
 pub(crate) struct TracingHarness {
     tracer: Tracer,
+    sampler: ReloadableSampler,
 
     pub(crate) span_scope_stack: ScopeStack<SharedSpan>,
 
@@ -47,6 +108,10 @@ pub(crate) struct TracingHarness {
 
     pub(super) active_roots: Arc<LiveReferenceSet<Arc<parking_lot::RwLock<Span>>>>,
     tracing_start: SystemTime,
+
+    // `None` for the noop harness and whenever tracing is disabled, since there's no
+    // reporter task running to flush or shut down.
+    reporter: Option<ReporterHandle>,
 }
 
 impl TracingHarness {
@@ -70,41 +135,103 @@ impl TracingHarness {
     pub(crate) fn get_active_traces(&self) -> String {
         spans_to_trace_events(self.tracing_start, &self.active_roots.get_live_references())
     }
+
+    /// Flushes spans currently buffered by the reporter task, returning once the
+    /// in-flight batch has been sent. A no-op if tracing is disabled or not yet
+    /// initialized.
+    pub(crate) async fn force_flush(&self) {
+        let Some(reporter) = &self.reporter else {
+            return;
+        };
+
+        let (ack_tx, ack_rx) = oneshot::channel();
+
+        if reporter
+            .control_tx
+            .send(ReporterControl::Flush(ack_tx))
+            .is_ok()
+        {
+            let _ = ack_rx.await;
+        }
+    }
+
+    /// Flushes any buffered spans, stops the reporter task, and waits for it to finish.
+    /// A no-op if tracing is disabled or not yet initialized. Intended for short-lived
+    /// services and tests that would otherwise lose the tail of their traces on exit.
+    pub(crate) async fn shutdown(&self) {
+        let Some(reporter) = &self.reporter else {
+            return;
+        };
+
+        let (ack_tx, ack_rx) = oneshot::channel();
+
+        if reporter
+            .control_tx
+            .send(ReporterControl::Shutdown(ack_tx))
+            .is_ok()
+        {
+            let _ = ack_rx.await;
+        }
+
+        if let Some(join_handle) = reporter.join_handle.lock().await.take() {
+            let _ = join_handle.await;
+        }
+    }
+
+    /// Rebuilds the sampler from `strategy` and installs it live, without rebuilding the
+    /// `Tracer`, restarting the reporter task, or touching the span channel. This is what
+    /// lets e.g. the rate-limited probabilistic sampler's rate be ramped up during an
+    /// incident without a process restart.
+    pub(crate) fn set_sampling_strategy(&self, strategy: &SamplingStrategy) -> BootstrapResult<()> {
+        self.sampler.swap(sampler_for_strategy(strategy)?);
+
+        Ok(())
+    }
 }
 
 pub(crate) fn create_tracer_and_span_rx(
     settings: &TracingSettings,
-) -> BootstrapResult<(Tracer, SpanReceiver)> {
-    let sampler = match &settings.sampling_strategy {
-        SamplingStrategy::Passive => PassiveSampler.boxed(),
-        SamplingStrategy::Active(settings) => {
-            RateLimitingProbabilisticSampler::new(settings)?.boxed()
-        }
-    };
+) -> BootstrapResult<(Tracer, SpanReceiver, ReloadableSampler)> {
+    let sampler = ReloadableSampler::new(sampler_for_strategy(&settings.sampling_strategy)?);
+    let (tracer, span_rx) = Tracer::new(sampler.clone().boxed());
 
-    Ok(Tracer::new(sampler))
+    Ok((tracer, span_rx, sampler))
 }
 
 // NOTE: does nothing if tracing has already been initialized in this process.
-pub(crate) fn init(
-    service_info: ServiceInfo,
-    settings: &TracingSettings,
-) -> BootstrapResult<Option<BoxFuture<'static, BootstrapResult<()>>>> {
-    let reporter_fut = if settings.enabled {
-        let (tracer, span_rx) = create_tracer_and_span_rx(settings)?;
+pub(crate) fn init(service_info: ServiceInfo, settings: &TracingSettings) -> BootstrapResult<()> {
+    if settings.enabled {
+        let (tracer, span_rx, sampler) = create_tracer_and_span_rx(settings)?;
+        let (control_tx, control_rx) = control_mpsc::unbounded_channel();
+
+        let (span_rx, control_rx) = match &settings.tail_sampling {
+            Some(tail_sampling_settings) => {
+                tail_sampling::wrap(tail_sampling_settings.clone(), span_rx, control_rx)
+            }
+            None => (span_rx, control_rx),
+        };
 
         let reporter_fut = match &settings.output {
             TracesOutput::JaegerThriftUdp(output_settings) => {
-                output_jaeger_thrift_udp::start(service_info, output_settings, span_rx)?
+                output_jaeger_thrift_udp::start(service_info, output_settings, span_rx, control_rx)?
             }
             #[cfg(feature = "telemetry-otlp-grpc")]
             TracesOutput::OpenTelemetryGrpc(output_settings) => {
-                output_otlp_grpc::start(service_info, output_settings, span_rx)?
+                output_otlp_grpc::start(service_info, output_settings, span_rx, control_rx)?
+            }
+            TracesOutput::Zipkin(output_settings) => {
+                output_zipkin::start(service_info, output_settings, span_rx, control_rx)?
+            }
+            TracesOutput::File(output_settings) => {
+                output_file::start(service_info, output_settings, span_rx, control_rx)?
             }
         };
 
+        let join_handle = tokio::spawn(reporter_fut);
+
         let harness = TracingHarness {
             tracer,
+            sampler,
             span_scope_stack: Default::default(),
 
             #[cfg(feature = "testing")]
@@ -112,14 +239,44 @@ pub(crate) fn init(
 
             active_roots: Default::default(),
             tracing_start: SystemTime::now(),
+            reporter: Some(ReporterHandle {
+                control_tx,
+                join_handle: AsyncMutex::new(Some(join_handle)),
+            }),
         };
 
         let _ = HARNESS.set(harness);
+    }
+
+    Ok(())
+}
 
-        Some(reporter_fut)
-    } else {
-        None
-    };
+/// Flushes spans currently buffered by the tracing reporter, returning once the in-flight
+/// batch has been sent. A no-op if tracing is disabled or hasn't been initialized.
+///
+/// Call this from a health check or a periodic task in services that want a bound on how
+/// stale the trace backend's view of in-flight work can get, without waiting for
+/// [`shutdown_tracing`].
+pub async fn flush_tracing() {
+    TracingHarness::get().force_flush().await;
+}
+
+/// Flushes any buffered spans, stops the reporter task, and waits for it to finish. A no-op
+/// if tracing is disabled or hasn't been initialized.
+///
+/// Call this as part of a service's graceful shutdown sequence so the tail of its traces
+/// isn't lost when the process exits.
+pub async fn shutdown_tracing() {
+    TracingHarness::get().shutdown().await;
+}
 
-    Ok(reporter_fut)
+/// Rebuilds the sampler from `strategy` and installs it live, without rebuilding the
+/// `Tracer`, restarting the reporter task, or touching the span channel. A no-op if tracing
+/// is disabled or hasn't been initialized.
+///
+/// `TracingHarness::set_sampling_strategy` is crate-private, so without this there was no
+/// way for a service to actually reach the runtime sampling reload the request asked for
+/// (e.g. from an admin endpoint that ramps sampling up during an incident).
+pub fn set_sampling_strategy(strategy: &SamplingStrategy) -> BootstrapResult<()> {
+    TracingHarness::get().set_sampling_strategy(strategy)
 }
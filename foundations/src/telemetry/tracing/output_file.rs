@@ -0,0 +1,279 @@
+//! Rolling-file span output, for deployments without network access to a trace collector.
+//!
+//! Completed spans are serialized one-per-line as newline-delimited JSON on disk (reusing
+//! [`spans_to_trace_events`]), rotating into a new file every hour, or sooner if the
+//! current file reaches [`FileOutputSettings::max_file_size_bytes`], so that a long-running
+//! or high-volume process doesn't accumulate one unbounded trace file. This complements the
+//! existing [`TracingHarness::get_active_traces`](super::init::TracingHarness::get_active_traces)
+//! debug dump with a durable sink that can be replayed later.
+
+use super::event_output::spans_to_trace_events;
+use super::init::{ReporterControl, ReporterControlReceiver};
+use crate::telemetry::settings::FileOutputSettings;
+use crate::{BootstrapResult, ServiceInfo};
+use cf_rustracing_jaeger::span::{FinishedSpan, SpanReceiver};
+use futures_util::future::BoxFuture;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+
+const MAX_BATCH_SIZE: usize = 100;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Builds the rotated file name `prefix.YYYY-MM-DD-HH.suffix`, omitting the separating dot
+/// on either side when `prefix` or `suffix` is empty. `sequence` is appended as
+/// `-{sequence}` to the date component when a file is rotated by size within the same
+/// hour (`sequence` `0` is the first file of the hour and adds no suffix).
+fn rotated_file_name(prefix: &str, suffix: &str, date_component: &str, sequence: u32) -> String {
+    let mut name = String::new();
+
+    if !prefix.is_empty() {
+        name.push_str(prefix);
+        name.push('.');
+    }
+
+    name.push_str(date_component);
+
+    if sequence > 0 {
+        name.push('-');
+        name.push_str(&sequence.to_string());
+    }
+
+    if !suffix.is_empty() {
+        name.push('.');
+        name.push_str(suffix);
+    }
+
+    name
+}
+
+/// Formats `time` as `YYYY-MM-DD-HH` in UTC, using Howard Hinnant's civil-from-days
+/// algorithm so rotation doesn't need to pull in a date/time crate just for this.
+fn date_hour_component(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs() as i64;
+
+    let days = secs.div_euclid(86_400);
+    let hour = secs.rem_euclid(86_400) / 3_600;
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = if m <= 2 { y + 1 } else { y };
+
+    format!("{y:04}-{m:02}-{d:02}-{hour:02}")
+}
+
+struct RotatingWriter {
+    directory: PathBuf,
+    filename_prefix: String,
+    filename_suffix: String,
+    max_file_size_bytes: u64,
+    current_hour: Option<String>,
+    sequence: u32,
+    current_size: u64,
+    file: Option<tokio::fs::File>,
+}
+
+impl RotatingWriter {
+    fn new(
+        directory: PathBuf,
+        filename_prefix: String,
+        filename_suffix: String,
+        max_file_size_bytes: u64,
+    ) -> Self {
+        Self {
+            directory,
+            filename_prefix,
+            filename_suffix,
+            max_file_size_bytes,
+            current_hour: None,
+            sequence: 0,
+            current_size: 0,
+            file: None,
+        }
+    }
+
+    async fn write_line(&mut self, line: &str) -> BootstrapResult<()> {
+        let hour = date_hour_component(SystemTime::now());
+
+        if self.current_hour.as_deref() != Some(&hour) {
+            self.current_hour = Some(hour);
+            self.sequence = 0;
+            self.current_size = 0;
+            self.file = None;
+        } else if self.file.is_some() && self.current_size >= self.max_file_size_bytes {
+            self.sequence += 1;
+            self.current_size = 0;
+            self.file = None;
+        }
+
+        if self.file.is_none() {
+            let name = rotated_file_name(
+                &self.filename_prefix,
+                &self.filename_suffix,
+                self.current_hour.as_deref().expect("just set above"),
+                self.sequence,
+            );
+
+            self.file = Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(self.directory.join(&name))
+                    .await?,
+            );
+        }
+
+        let file = self.file.as_mut().expect("just opened above");
+        file.write_all(line.as_bytes()).await?;
+        file.write_all(b"\n").await?;
+        self.current_size += line.len() as u64 + 1;
+
+        Ok(())
+    }
+}
+
+async fn reporter_loop(
+    settings: FileOutputSettings,
+    mut span_rx: SpanReceiver,
+    mut control_rx: ReporterControlReceiver,
+) -> BootstrapResult<()> {
+    let tracing_start = SystemTime::now();
+    let mut writer = RotatingWriter::new(
+        settings.directory.clone(),
+        settings.filename_prefix.clone(),
+        settings.filename_suffix.clone(),
+        settings.max_file_size_bytes,
+    );
+
+    let mut batch = Vec::with_capacity(MAX_BATCH_SIZE);
+    let mut flush_interval = tokio::time::interval(FLUSH_INTERVAL);
+
+    loop {
+        tokio::select! {
+            biased;
+
+            control = control_rx.recv() => {
+                match control {
+                    Some(ReporterControl::Flush(ack)) => {
+                        flush_batch(&mut writer, tracing_start, &mut batch).await?;
+                        let _ = ack.send(());
+                    }
+                    Some(ReporterControl::Shutdown(ack)) => {
+                        span_rx.close();
+
+                        while let Ok(span) = span_rx.try_recv() {
+                            batch.push(span);
+                        }
+
+                        flush_batch(&mut writer, tracing_start, &mut batch).await?;
+                        let _ = ack.send(());
+
+                        return Ok(());
+                    }
+                    None => return Ok(()),
+                }
+            }
+
+            _ = flush_interval.tick() => {
+                flush_batch(&mut writer, tracing_start, &mut batch).await?;
+            }
+
+            span = span_rx.recv() => {
+                let Some(span) = span else { return Ok(()) };
+
+                batch.push(span);
+
+                if batch.len() >= MAX_BATCH_SIZE {
+                    flush_batch(&mut writer, tracing_start, &mut batch).await?;
+                }
+            }
+        }
+    }
+}
+
+/// Writes one line per span, each the output of `spans_to_trace_events` for that single
+/// span, so that the file is genuine newline-delimited JSON (one record per line) rather
+/// than one aggregate JSON array per batch.
+async fn flush_batch(
+    writer: &mut RotatingWriter,
+    tracing_start: SystemTime,
+    batch: &mut Vec<FinishedSpan>,
+) -> BootstrapResult<()> {
+    for span in batch.drain(..) {
+        let line = spans_to_trace_events(tracing_start, std::slice::from_ref(&span));
+        writer.write_line(&line).await?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn start(
+    _service_info: ServiceInfo,
+    settings: &FileOutputSettings,
+    span_rx: SpanReceiver,
+    control_rx: ReporterControlReceiver,
+) -> BootstrapResult<BoxFuture<'static, BootstrapResult<()>>> {
+    let settings = settings.clone();
+
+    Ok(Box::pin(reporter_loop(settings, span_rx, control_rx)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn date_hour_component_formats_utc() {
+        // 2024-01-02T03:04:05Z
+        let time = UNIX_EPOCH + Duration::from_secs(1_704_164_645);
+        assert_eq!(date_hour_component(time), "2024-01-02-03");
+    }
+
+    #[test]
+    fn date_hour_component_handles_epoch() {
+        assert_eq!(date_hour_component(UNIX_EPOCH), "1970-01-01-00");
+    }
+
+    #[test]
+    fn rotated_file_name_includes_both_prefix_and_suffix() {
+        assert_eq!(
+            rotated_file_name("traces", "jsonl", "2024-01-02-03", 0),
+            "traces.2024-01-02-03.jsonl"
+        );
+    }
+
+    #[test]
+    fn rotated_file_name_skips_separator_when_prefix_empty() {
+        assert_eq!(
+            rotated_file_name("", "jsonl", "2024-01-02-03", 0),
+            "2024-01-02-03.jsonl"
+        );
+    }
+
+    #[test]
+    fn rotated_file_name_skips_separator_when_suffix_empty() {
+        assert_eq!(
+            rotated_file_name("traces", "", "2024-01-02-03", 0),
+            "traces.2024-01-02-03"
+        );
+    }
+
+    #[test]
+    fn rotated_file_name_appends_sequence_for_size_based_rotation() {
+        assert_eq!(
+            rotated_file_name("traces", "jsonl", "2024-01-02-03", 2),
+            "traces.2024-01-02-03-2.jsonl"
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! Jaeger Thrift-over-UDP trace reporter.
+//!
+//! Batches spans off the [`SpanReceiver`] and reports them to a local Jaeger agent using
+//! the Thrift compact protocol over UDP.
+
+use super::init::{ReporterControl, ReporterControlReceiver};
+use crate::telemetry::settings::JaegerThriftUdpOutputSettings;
+use crate::{BootstrapResult, ServiceInfo};
+use cf_rustracing_jaeger::reporter::JaegerCompactReporter;
+use cf_rustracing_jaeger::span::{FinishedSpan, SpanReceiver};
+use futures_util::future::BoxFuture;
+use std::time::Duration;
+
+const MAX_BATCH_SIZE: usize = 100;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+fn report_batch(reporter: &JaegerCompactReporter, batch: &mut Vec<FinishedSpan>) -> BootstrapResult<()> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+
+    reporter.report(batch)?;
+    batch.clear();
+
+    Ok(())
+}
+
+async fn reporter_loop(
+    service_info: ServiceInfo,
+    settings: JaegerThriftUdpOutputSettings,
+    mut span_rx: SpanReceiver,
+    mut control_rx: ReporterControlReceiver,
+) -> BootstrapResult<()> {
+    let mut reporter = JaegerCompactReporter::new(&service_info.name)?;
+    reporter.set_agent_addr(settings.agent_addr);
+
+    let mut batch = Vec::with_capacity(MAX_BATCH_SIZE);
+    let mut flush_interval = tokio::time::interval(FLUSH_INTERVAL);
+
+    loop {
+        tokio::select! {
+            biased;
+
+            control = control_rx.recv() => {
+                match control {
+                    Some(ReporterControl::Flush(ack)) => {
+                        report_batch(&reporter, &mut batch)?;
+                        let _ = ack.send(());
+                    }
+                    Some(ReporterControl::Shutdown(ack)) => {
+                        span_rx.close();
+
+                        while let Ok(span) = span_rx.try_recv() {
+                            batch.push(span);
+                        }
+
+                        report_batch(&reporter, &mut batch)?;
+                        let _ = ack.send(());
+
+                        return Ok(());
+                    }
+                    None => return Ok(()),
+                }
+            }
+
+            _ = flush_interval.tick() => {
+                report_batch(&reporter, &mut batch)?;
+            }
+
+            span = span_rx.recv() => {
+                let Some(span) = span else { return Ok(()) };
+
+                batch.push(span);
+
+                if batch.len() >= MAX_BATCH_SIZE {
+                    report_batch(&reporter, &mut batch)?;
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn start(
+    service_info: ServiceInfo,
+    settings: &JaegerThriftUdpOutputSettings,
+    span_rx: SpanReceiver,
+    control_rx: ReporterControlReceiver,
+) -> BootstrapResult<BoxFuture<'static, BootstrapResult<()>>> {
+    let settings = settings.clone();
+
+    Ok(Box::pin(reporter_loop(
+        service_info,
+        settings,
+        span_rx,
+        control_rx,
+    )))
+}
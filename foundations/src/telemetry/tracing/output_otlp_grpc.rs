@@ -0,0 +1,165 @@
+//! OTLP/gRPC trace reporter.
+//!
+//! Batches spans off the [`SpanReceiver`] and exports them to an OTLP-compatible
+//! collector over gRPC via the generated `TraceServiceClient`.
+
+use super::init::{ReporterControl, ReporterControlReceiver};
+use crate::telemetry::settings::OpenTelemetryGrpcOutputSettings;
+use crate::{BootstrapResult, ServiceInfo};
+use cf_rustracing_jaeger::span::{FinishedSpan, SpanReceiver};
+use futures_util::future::BoxFuture;
+use opentelemetry_proto::tonic::collector::trace::v1::{
+    trace_service_client::TraceServiceClient, ExportTraceServiceRequest,
+};
+use opentelemetry_proto::tonic::common::v1::{any_value::Value as AnyValueKind, AnyValue, KeyValue};
+use opentelemetry_proto::tonic::resource::v1::Resource;
+use opentelemetry_proto::tonic::trace::v1::{ResourceSpans, ScopeSpans, Span as OtlpSpan};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tonic::transport::Channel;
+
+const MAX_BATCH_SIZE: usize = 100;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+fn nanos_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_nanos() as u64
+}
+
+fn span_to_otlp_span(span: &FinishedSpan) -> OtlpSpan {
+    let ctx = span.context().state();
+
+    let parent_span_id = span
+        .references()
+        .iter()
+        .find_map(|r| r.span().map(|s| s.span_id().to_be_bytes().to_vec()))
+        .unwrap_or_default();
+
+    OtlpSpan {
+        trace_id: ctx.trace_id().as_u128().to_be_bytes().to_vec(),
+        span_id: ctx.span_id().to_be_bytes().to_vec(),
+        parent_span_id,
+        name: span.operation_name().to_owned(),
+        start_time_unix_nano: nanos_since_epoch(span.start_time()),
+        end_time_unix_nano: nanos_since_epoch(span.finish_time()),
+        ..Default::default()
+    }
+}
+
+/// Builds the OTLP `Resource` identifying which service these spans came from, via the
+/// standard `service.name` resource attribute collectors use to populate a service map.
+fn resource_for(service_info: &ServiceInfo) -> Resource {
+    Resource {
+        attributes: vec![KeyValue {
+            key: "service.name".to_owned(),
+            value: Some(AnyValue {
+                value: Some(AnyValueKind::StringValue(service_info.name.to_string())),
+            }),
+        }],
+        dropped_attributes_count: 0,
+    }
+}
+
+fn batch_to_request(service_info: &ServiceInfo, batch: Vec<FinishedSpan>) -> ExportTraceServiceRequest {
+    let spans = batch.iter().map(span_to_otlp_span).collect();
+
+    ExportTraceServiceRequest {
+        resource_spans: vec![ResourceSpans {
+            resource: Some(resource_for(service_info)),
+            scope_spans: vec![ScopeSpans {
+                spans,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+    }
+}
+
+async fn report_batch(
+    client: &mut TraceServiceClient<Channel>,
+    service_info: &ServiceInfo,
+    batch: Vec<FinishedSpan>,
+) -> BootstrapResult<()> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .export(batch_to_request(service_info, batch))
+        .await?;
+
+    Ok(())
+}
+
+async fn reporter_loop(
+    service_info: ServiceInfo,
+    settings: OpenTelemetryGrpcOutputSettings,
+    mut span_rx: SpanReceiver,
+    mut control_rx: ReporterControlReceiver,
+) -> BootstrapResult<()> {
+    let channel = Channel::from_shared(settings.endpoint.clone())?
+        .connect()
+        .await?;
+    let mut client = TraceServiceClient::new(channel);
+
+    let mut batch = Vec::with_capacity(MAX_BATCH_SIZE);
+    let mut flush_interval = tokio::time::interval(FLUSH_INTERVAL);
+
+    loop {
+        tokio::select! {
+            biased;
+
+            control = control_rx.recv() => {
+                match control {
+                    Some(ReporterControl::Flush(ack)) => {
+                        report_batch(&mut client, &service_info, std::mem::take(&mut batch)).await?;
+                        let _ = ack.send(());
+                    }
+                    Some(ReporterControl::Shutdown(ack)) => {
+                        span_rx.close();
+
+                        while let Ok(span) = span_rx.try_recv() {
+                            batch.push(span);
+                        }
+
+                        report_batch(&mut client, &service_info, std::mem::take(&mut batch)).await?;
+                        let _ = ack.send(());
+
+                        return Ok(());
+                    }
+                    None => return Ok(()),
+                }
+            }
+
+            _ = flush_interval.tick() => {
+                report_batch(&mut client, &service_info, std::mem::take(&mut batch)).await?;
+            }
+
+            span = span_rx.recv() => {
+                let Some(span) = span else { return Ok(()) };
+
+                batch.push(span);
+
+                if batch.len() >= MAX_BATCH_SIZE {
+                    report_batch(&mut client, &service_info, std::mem::take(&mut batch)).await?;
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn start(
+    service_info: ServiceInfo,
+    settings: &OpenTelemetryGrpcOutputSettings,
+    span_rx: SpanReceiver,
+    control_rx: ReporterControlReceiver,
+) -> BootstrapResult<BoxFuture<'static, BootstrapResult<()>>> {
+    let settings = settings.clone();
+
+    Ok(Box::pin(reporter_loop(
+        service_info,
+        settings,
+        span_rx,
+        control_rx,
+    )))
+}
@@ -0,0 +1,218 @@
+//! Zipkin v2 JSON/HTTP trace reporter.
+//!
+//! Spans are batched off the [`SpanReceiver`] and POSTed as a Zipkin v2 span array to a
+//! configurable collector URL, giving foundations users a path to the large installed base
+//! of Zipkin-compatible collectors without running an OTLP or Jaeger agent.
+
+use super::init::{ReporterControl, ReporterControlReceiver};
+use crate::telemetry::settings::ZipkinOutputSettings;
+use crate::{BootstrapResult, ServiceInfo};
+use cf_rustracing_jaeger::span::{FinishedSpan, SpanReceiver};
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Maximum number of spans buffered before they're flushed to the collector, even if the
+/// flush interval hasn't elapsed yet.
+const MAX_BATCH_SIZE: usize = 100;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize)]
+struct ZipkinEndpoint {
+    #[serde(rename = "serviceName")]
+    service_name: String,
+    #[serde(rename = "ipv4", skip_serializing_if = "Option::is_none")]
+    ipv4: Option<String>,
+    #[serde(rename = "port", skip_serializing_if = "Option::is_none")]
+    port: Option<u16>,
+}
+
+#[derive(Debug, Serialize)]
+struct ZipkinAnnotation {
+    timestamp: u64,
+    value: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ZipkinSpan {
+    #[serde(rename = "traceId")]
+    trace_id: String,
+    id: String,
+    #[serde(rename = "parentId", skip_serializing_if = "Option::is_none")]
+    parent_id: Option<String>,
+    name: String,
+    timestamp: u64,
+    duration: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<&'static str>,
+    #[serde(rename = "localEndpoint")]
+    local_endpoint: ZipkinEndpoint,
+    tags: std::collections::BTreeMap<String, String>,
+    annotations: Vec<ZipkinAnnotation>,
+}
+
+fn micros_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_micros() as u64
+}
+
+/// Derives the Zipkin `kind` from the span's `span.kind` OpenTracing tag. `kind` is optional
+/// in the Zipkin v2 spec, and is left unset (rather than defaulted to `SERVER`) for spans
+/// that don't carry a `span.kind` tag, since guessing `SERVER` for untyped/internal spans
+/// would corrupt Zipkin's service-map and client/server latency breakdowns.
+fn span_kind(span: &FinishedSpan) -> Option<&'static str> {
+    span.tags()
+        .iter()
+        .find(|tag| tag.name() == "span.kind")
+        .map(|tag| match tag.value().to_string().as_str() {
+            "client" => "CLIENT",
+            "producer" => "PRODUCER",
+            "consumer" => "CONSUMER",
+            _ => "SERVER",
+        })
+}
+
+fn span_to_zipkin_span(span: &FinishedSpan, local_endpoint: &ZipkinEndpoint) -> ZipkinSpan {
+    let ctx = span.context().state();
+
+    let parent_id = span
+        .references()
+        .iter()
+        .find_map(|r| r.span().map(|s| format!("{:016x}", s.span_id())));
+
+    let tags = span
+        .tags()
+        .iter()
+        .map(|tag| (tag.name().to_owned(), tag.value().to_string()))
+        .collect();
+
+    let annotations = span
+        .logs()
+        .iter()
+        .map(|log| ZipkinAnnotation {
+            timestamp: micros_since_epoch(log.time()),
+            value: log
+                .fields()
+                .iter()
+                .map(|f| format!("{}={}", f.name(), f.value()))
+                .collect::<Vec<_>>()
+                .join(" "),
+        })
+        .collect();
+
+    let start = micros_since_epoch(span.start_time());
+    let duration = span
+        .finish_time()
+        .duration_since(span.start_time())
+        .unwrap_or(Duration::ZERO)
+        .as_micros() as u64;
+
+    ZipkinSpan {
+        trace_id: format!("{:032x}", ctx.trace_id().as_u128()),
+        id: format!("{:016x}", ctx.span_id()),
+        parent_id,
+        name: span.operation_name().to_owned(),
+        timestamp: start,
+        duration,
+        kind: span_kind(span),
+        local_endpoint: local_endpoint.clone(),
+        tags,
+        annotations,
+    }
+}
+
+async fn report_batch(
+    client: &reqwest::Client,
+    collector_url: &str,
+    batch: Vec<ZipkinSpan>,
+) -> BootstrapResult<()> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .post(collector_url)
+        .json(&batch)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+async fn reporter_loop(
+    service_info: ServiceInfo,
+    settings: ZipkinOutputSettings,
+    mut span_rx: SpanReceiver,
+    mut control_rx: ReporterControlReceiver,
+) -> BootstrapResult<()> {
+    let client = reqwest::Client::new();
+
+    let local_endpoint = ZipkinEndpoint {
+        service_name: service_info.name.to_string(),
+        ipv4: settings.local_ipv4.map(|ip| ip.to_string()),
+        port: settings.local_port,
+    };
+
+    let mut batch = Vec::with_capacity(MAX_BATCH_SIZE);
+    let mut flush_interval = tokio::time::interval(FLUSH_INTERVAL);
+
+    loop {
+        tokio::select! {
+            biased;
+
+            control = control_rx.recv() => {
+                match control {
+                    Some(ReporterControl::Flush(ack)) => {
+                        report_batch(&client, &settings.collector_url, std::mem::take(&mut batch)).await?;
+                        let _ = ack.send(());
+                    }
+                    Some(ReporterControl::Shutdown(ack)) => {
+                        span_rx.close();
+
+                        while let Ok(span) = span_rx.try_recv() {
+                            batch.push(span_to_zipkin_span(&span, &local_endpoint));
+                        }
+
+                        report_batch(&client, &settings.collector_url, std::mem::take(&mut batch)).await?;
+                        let _ = ack.send(());
+
+                        return Ok(());
+                    }
+                    None => return Ok(()),
+                }
+            }
+
+            _ = flush_interval.tick() => {
+                report_batch(&client, &settings.collector_url, std::mem::take(&mut batch)).await?;
+            }
+
+            span = span_rx.recv() => {
+                let Some(span) = span else { return Ok(()) };
+
+                batch.push(span_to_zipkin_span(&span, &local_endpoint));
+
+                if batch.len() >= MAX_BATCH_SIZE {
+                    report_batch(&client, &settings.collector_url, std::mem::take(&mut batch)).await?;
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn start(
+    service_info: ServiceInfo,
+    settings: &ZipkinOutputSettings,
+    span_rx: SpanReceiver,
+    control_rx: ReporterControlReceiver,
+) -> BootstrapResult<BoxFuture<'static, BootstrapResult<()>>> {
+    let settings = settings.clone();
+
+    Ok(Box::pin(reporter_loop(
+        service_info,
+        settings,
+        span_rx,
+        control_rx,
+    )))
+}
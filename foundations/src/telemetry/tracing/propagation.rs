@@ -0,0 +1,301 @@
+//! Cross-process trace context propagation.
+//!
+//! The harness creates and scopes spans locally, but a service sitting in the middle of a
+//! call graph needs to continue a trace it received from an upstream caller and forward it
+//! downstream, rather than starting disconnected per-service traces. [`extract_context`]
+//! parses an inbound carrier (e.g. HTTP headers) into a [`SpanContext`]; [`inject_context`]
+//! serializes the currently scoped span back out so it can be attached to an outbound
+//! request.
+//!
+//! Two wire formats are supported behind the same API so that HTTP middlewares can choose
+//! one: Jaeger's single-header `uber-trace-id`, and the W3C `traceparent` header.
+//!
+//! Both functions are `pub`: unlike the rest of this module, there's no call site for them
+//! inside `foundations` itself, since the crate doesn't ship an HTTP server or client of its
+//! own. They're meant to be called directly from a service's HTTP middleware, at the point
+//! where it has access to the inbound/outbound request headers.
+//!
+//! **Scope note:** turning an `extract_context` result back into the parent of a newly
+//! started root span isn't implemented here. `cf_rustracing_jaeger`'s span-starting API
+//! takes a reference to an existing, live `SpanContext` (as produced by this crate's own
+//! `Tracer`), not a bag of raw trace/span ids reconstructed from a wire format — bridging
+//! the two needs a constructor on the tracer/span-starting path this module doesn't own.
+//! `SpanContext` is deliberately a plain data type for now; wiring it into span creation is
+//! a separate piece of work, not something this module can commit without overclaiming.
+
+use super::init::TracingHarness;
+use std::collections::HashMap;
+
+const JAEGER_TRACE_HEADER: &str = "uber-trace-id";
+const W3C_TRACE_HEADER: &str = "traceparent";
+
+const JAEGER_FLAG_SAMPLED: u64 = 0b01;
+const JAEGER_FLAG_DEBUG: u64 = 0b10;
+
+const W3C_FLAG_SAMPLED: u8 = 0b0000_0001;
+
+/// The identity of a span as it crosses a process boundary: enough to continue a trace
+/// without pulling the full `cf_rustracing_jaeger` span machinery across the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpanContext {
+    pub trace_id_high: u64,
+    pub trace_id_low: u64,
+    pub span_id: u64,
+    pub parent_span_id: Option<u64>,
+    pub sampled: bool,
+    pub debug: bool,
+}
+
+/// Wire format used to encode/decode a [`SpanContext`] on a carrier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropagationFormat {
+    /// Jaeger's single `uber-trace-id` header.
+    Jaeger,
+    /// The W3C Trace Context `traceparent` header.
+    W3c,
+}
+
+/// Extracts a [`SpanContext`] from an inbound carrier (e.g. HTTP request headers),
+/// trying the Jaeger `uber-trace-id` header first and then the W3C `traceparent` header.
+/// Returns `None` if neither header is present or well-formed.
+pub fn extract_context(carrier: &HashMap<String, String>) -> Option<SpanContext> {
+    if let Some(header) = carrier.get(JAEGER_TRACE_HEADER) {
+        if let Some(ctx) = decode_jaeger(header) {
+            return Some(ctx);
+        }
+    }
+
+    carrier.get(W3C_TRACE_HEADER).and_then(|h| decode_w3c(h))
+}
+
+/// Serializes the span currently at the top of the scope stack onto an outbound carrier,
+/// in the given wire format. A no-op if there's no span currently scoped.
+pub fn inject_context(carrier: &mut HashMap<String, String>, format: PropagationFormat) {
+    let Some(ctx) = current_span_context() else {
+        return;
+    };
+
+    match format {
+        PropagationFormat::Jaeger => {
+            carrier.insert(JAEGER_TRACE_HEADER.to_owned(), encode_jaeger(&ctx));
+        }
+        PropagationFormat::W3c => {
+            carrier.insert(W3C_TRACE_HEADER.to_owned(), encode_w3c(&ctx));
+        }
+    }
+}
+
+fn current_span_context() -> Option<SpanContext> {
+    let span = TracingHarness::get().span_scope_stack.current()?;
+    let span = span.read();
+    let ctx = span.context()?;
+    let state = ctx.state();
+
+    // `SpanContextState` has no `parent_id()` of its own; a span's parent is recorded as a
+    // reference taken at start time, the same place `output_zipkin.rs` reads it from when
+    // building a Zipkin span's `parentId`.
+    let parent_span_id = span
+        .references()
+        .iter()
+        .find_map(|r| r.span().map(|s| s.span_id()));
+
+    Some(SpanContext {
+        trace_id_high: state.trace_id().high(),
+        trace_id_low: state.trace_id().low(),
+        span_id: state.span_id(),
+        parent_span_id,
+        sampled: state.is_sampled(),
+        debug: state.is_debug(),
+    })
+}
+
+fn decode_jaeger(header: &str) -> Option<SpanContext> {
+    let mut parts = header.split(':');
+
+    let trace_id_hex = parts.next()?;
+    let span_id_hex = parts.next()?;
+    let parent_span_id_hex = parts.next()?;
+    let flags_hex = parts.next()?;
+
+    if parts.next().is_some() || trace_id_hex.is_empty() {
+        return None;
+    }
+
+    let (trace_id_high, trace_id_low) = split_trace_id_hex(trace_id_hex)?;
+    let span_id = u64::from_str_radix(span_id_hex, 16).ok()?;
+    let parent_span_id = u64::from_str_radix(parent_span_id_hex, 16).ok().filter(|&id| id != 0);
+    let flags = u64::from_str_radix(flags_hex, 16).ok()?;
+
+    Some(SpanContext {
+        trace_id_high,
+        trace_id_low,
+        span_id,
+        parent_span_id,
+        sampled: flags & JAEGER_FLAG_SAMPLED != 0,
+        debug: flags & JAEGER_FLAG_DEBUG != 0,
+    })
+}
+
+fn encode_jaeger(ctx: &SpanContext) -> String {
+    let flags =
+        (ctx.sampled as u64) * JAEGER_FLAG_SAMPLED | (ctx.debug as u64) * JAEGER_FLAG_DEBUG;
+
+    format!(
+        "{:016x}{:016x}:{:016x}:{:016x}:{:x}",
+        ctx.trace_id_high,
+        ctx.trace_id_low,
+        ctx.span_id,
+        ctx.parent_span_id.unwrap_or(0),
+        flags
+    )
+}
+
+fn decode_w3c(header: &str) -> Option<SpanContext> {
+    let mut parts = header.split('-');
+
+    let version = parts.next()?;
+    let trace_id_hex = parts.next()?;
+    let span_id_hex = parts.next()?;
+    let flags_hex = parts.next()?;
+
+    if parts.next().is_some() || version != "00" || trace_id_hex.len() != 32 || span_id_hex.len() != 16 {
+        return None;
+    }
+
+    let (trace_id_high, trace_id_low) = split_trace_id_hex(trace_id_hex)?;
+    let span_id = u64::from_str_radix(span_id_hex, 16).ok()?;
+    let flags = u8::from_str_radix(flags_hex, 16).ok()?;
+
+    Some(SpanContext {
+        trace_id_high,
+        trace_id_low,
+        span_id,
+        parent_span_id: None,
+        sampled: flags & W3C_FLAG_SAMPLED != 0,
+        debug: false,
+    })
+}
+
+fn encode_w3c(ctx: &SpanContext) -> String {
+    let flags: u8 = if ctx.sampled { W3C_FLAG_SAMPLED } else { 0 };
+
+    format!(
+        "00-{:016x}{:016x}-{:016x}-{:02x}",
+        ctx.trace_id_high, ctx.trace_id_low, ctx.span_id, flags
+    )
+}
+
+/// Splits a hex-encoded trace id into its high/low 64-bit halves. Jaeger trace ids may be
+/// emitted as either 64-bit (16 hex chars, high half implicitly zero) or 128-bit (32 hex
+/// chars) values; W3C trace ids are always 128-bit.
+///
+/// `hex` comes straight off an inbound header, so it must be rejected outright if it isn't
+/// plain ASCII: `hex.len()` counts bytes, and slicing a `&str` at a byte offset that lands
+/// inside a multi-byte UTF-8 character panics rather than returning `None`.
+fn split_trace_id_hex(hex: &str) -> Option<(u64, u64)> {
+    if !hex.is_ascii() {
+        return None;
+    }
+
+    match hex.len() {
+        1..=16 => Some((0, u64::from_str_radix(hex, 16).ok()?)),
+        17..=32 => {
+            let split = hex.len() - 16;
+            let high = u64::from_str_radix(&hex[..split], 16).ok()?;
+            let low = u64::from_str_radix(&hex[split..], 16).ok()?;
+            Some((high, low))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_context() -> SpanContext {
+        SpanContext {
+            trace_id_high: 0x0102030405060708,
+            trace_id_low: 0x1112131415161718,
+            span_id: 0x2122232425262728,
+            parent_span_id: Some(0x3132333435363738),
+            sampled: true,
+            debug: false,
+        }
+    }
+
+    #[test]
+    fn jaeger_round_trips_through_encode_decode() {
+        let ctx = sample_context();
+        let decoded = decode_jaeger(&encode_jaeger(&ctx)).unwrap();
+
+        assert_eq!(decoded, ctx);
+    }
+
+    #[test]
+    fn jaeger_decode_treats_zero_parent_as_root() {
+        let ctx = decode_jaeger("0102030405060708:2122232425262728:0:1").unwrap();
+
+        assert_eq!(ctx.parent_span_id, None);
+    }
+
+    #[test]
+    fn jaeger_decode_rejects_malformed_header() {
+        assert!(decode_jaeger("not-a-valid-header").is_none());
+    }
+
+    #[test]
+    fn w3c_round_trips_through_encode_decode() {
+        let ctx = SpanContext {
+            parent_span_id: None,
+            debug: false,
+            ..sample_context()
+        };
+
+        let decoded = decode_w3c(&encode_w3c(&ctx)).unwrap();
+
+        assert_eq!(decoded, ctx);
+    }
+
+    #[test]
+    fn w3c_decode_rejects_unsupported_version() {
+        assert!(decode_w3c("01-01020304050607081112131415161718-2122232425262728-01").is_none());
+    }
+
+    #[test]
+    fn extract_context_prefers_jaeger_header_over_w3c() {
+        let mut carrier = HashMap::new();
+        carrier.insert(JAEGER_TRACE_HEADER.to_owned(), encode_jaeger(&sample_context()));
+        carrier.insert(
+            W3C_TRACE_HEADER.to_owned(),
+            "00-00000000000000000000000000000001-0000000000000002-01".to_owned(),
+        );
+
+        let ctx = extract_context(&carrier).unwrap();
+
+        assert_eq!(ctx.span_id, sample_context().span_id);
+    }
+
+    #[test]
+    fn split_trace_id_hex_handles_64_and_128_bit_ids() {
+        assert_eq!(split_trace_id_hex("2122232425262728"), Some((0, 0x2122232425262728)));
+        assert_eq!(
+            split_trace_id_hex("01020304050607082122232425262728"),
+            Some((0x0102030405060708, 0x2122232425262728))
+        );
+        assert_eq!(split_trace_id_hex("010203040506070821222324252627281"), None);
+        assert_eq!(split_trace_id_hex(""), None);
+    }
+
+    #[test]
+    fn split_trace_id_hex_rejects_non_ascii_without_panicking() {
+        // 17 bytes long (so it takes the 128-bit branch and would compute a split point),
+        // but the 'é' is 2 bytes, so a naive byte-offset slice would land mid-character.
+        assert_eq!(split_trace_id_hex("é123456789012345"), None);
+    }
+
+    #[test]
+    fn decode_jaeger_rejects_non_ascii_trace_id_without_panicking() {
+        assert!(decode_jaeger("é123456789012345:2122232425262728:0:1").is_none());
+    }
+}
@@ -0,0 +1,270 @@
+//! Tail-based sampling.
+//!
+//! Head-based sampling (`RateLimitingProbabilisticSampler` / `PassiveSampler`) decides
+//! whether to keep a trace at root-span creation, before its outcome is known, so
+//! interesting slow or errored traces are dropped just as often as uninteresting ones.
+//! This stage buffers a trace's spans (mirroring how [`super::init::TracingHarness`]
+//! tracks in-flight roots in its `active_roots` [`super::live_reference_set::LiveReferenceSet`])
+//! until the root span completes, then decides whether to keep the whole trace based on
+//! its outcome: always keep if any span carries an error tag or the root's duration is
+//! at least the configured threshold, otherwise keep with the configured probability.
+
+use super::init::{ReporterControl, ReporterControlReceiver};
+use crate::telemetry::settings::TailSamplingSettings;
+use cf_rustracing_jaeger::span::{FinishedSpan, SpanReceiver};
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+const FORWARD_CHANNEL_CAPACITY: usize = 1024;
+
+type TraceId = (u64, u64);
+
+fn trace_id_of(span: &FinishedSpan) -> TraceId {
+    let state = span.context().state();
+    (state.trace_id().high(), state.trace_id().low())
+}
+
+fn is_root(span: &FinishedSpan) -> bool {
+    span.references().is_empty()
+}
+
+/// True if any span in the trace carries an `error` tag set to `true`. A bare `error` tag
+/// isn't enough: standard OpenTracing instrumentation sometimes sets it explicitly to
+/// `false`, which must not force-keep an otherwise uninteresting trace.
+fn has_error_tag(spans: &[FinishedSpan]) -> bool {
+    spans.iter().any(|span| {
+        span.tags()
+            .iter()
+            .any(|tag| tag.name() == "error" && tag.value().to_string() == "true")
+    })
+}
+
+/// Per-trace buffer of items `T` (in production, a trace's [`FinishedSpan`]s), capped at
+/// `max_traces` traces so that a flood of roots that never complete (e.g. a client that
+/// disconnects mid-request) can't grow memory unbounded. Eviction is by insertion order,
+/// i.e. the oldest still-incomplete trace is dropped first. Generic over `T` so the
+/// eviction bookkeeping can be unit-tested without needing a real `FinishedSpan`.
+struct TraceBuffer<T> {
+    insertion_order: VecDeque<TraceId>,
+    items_by_trace: HashMap<TraceId, Vec<T>>,
+    max_traces: usize,
+}
+
+impl<T> TraceBuffer<T> {
+    fn new(max_traces: usize) -> Self {
+        Self {
+            insertion_order: VecDeque::new(),
+            items_by_trace: HashMap::new(),
+            max_traces,
+        }
+    }
+
+    fn push(&mut self, trace_id: TraceId, item: T) {
+        if !self.items_by_trace.contains_key(&trace_id) {
+            self.insertion_order.push_back(trace_id);
+
+            while self.insertion_order.len() > self.max_traces {
+                if let Some(evicted) = self.insertion_order.pop_front() {
+                    self.items_by_trace.remove(&evicted);
+                }
+            }
+        }
+
+        self.items_by_trace.entry(trace_id).or_default().push(item);
+    }
+
+    fn take(&mut self, trace_id: &TraceId) -> Vec<T> {
+        self.insertion_order.retain(|id| id != trace_id);
+        self.items_by_trace.remove(trace_id).unwrap_or_default()
+    }
+
+    /// Empties the buffer, returning every still-incomplete trace's items. Used on
+    /// shutdown so that traces whose root never arrived aren't silently dropped.
+    fn drain_all(&mut self) -> Vec<Vec<T>> {
+        self.insertion_order.clear();
+        self.items_by_trace.drain().map(|(_, items)| items).collect()
+    }
+
+    #[cfg(test)]
+    fn buffered_trace_ids(&self) -> Vec<TraceId> {
+        self.insertion_order.iter().copied().collect()
+    }
+}
+
+fn should_keep(settings: &TailSamplingSettings, root: &FinishedSpan, trace: &[FinishedSpan]) -> bool {
+    let root_duration = root
+        .finish_time()
+        .duration_since(root.start_time())
+        .unwrap_or(Duration::ZERO);
+
+    if has_error_tag(trace) || root_duration >= settings.latency_threshold {
+        return true;
+    }
+
+    rand::random::<f64>() < settings.sample_probability
+}
+
+/// Wraps `span_rx`/`control_rx` with a tail-sampling stage: spans are buffered per-trace
+/// until their root completes, then the whole trace is forwarded on the returned receiver
+/// if it's kept, or dropped otherwise. `ReporterControl::Flush` is passed straight through
+/// unchanged. On `ReporterControl::Shutdown`, any traces still waiting on their root are
+/// force-kept and forwarded too, so that shutting down doesn't silently lose them, before
+/// forwarding the shutdown itself downstream. Hand the returned receiver/control-receiver
+/// pair to the selected `TracesOutput` reporter in place of the originals.
+pub(crate) fn wrap(
+    settings: TailSamplingSettings,
+    mut span_rx: SpanReceiver,
+    mut control_rx: ReporterControlReceiver,
+) -> (SpanReceiver, ReporterControlReceiver) {
+    let (forward_span_tx, forward_span_rx) = tokio::sync::mpsc::channel(FORWARD_CHANNEL_CAPACITY);
+    let (forward_control_tx, forward_control_rx) = mpsc::unbounded_channel();
+    let mut buffer = TraceBuffer::new(settings.max_buffered_traces);
+
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                biased;
+
+                control = control_rx.recv() => {
+                    match control {
+                        Some(ReporterControl::Flush(ack)) => {
+                            if !relay_control(&forward_control_tx, ReporterControl::Flush, ack).await {
+                                return;
+                            }
+                        }
+                        Some(ReporterControl::Shutdown(ack)) => {
+                            span_rx.close();
+
+                            while let Ok(span) = span_rx.try_recv() {
+                                let trace_id = trace_id_of(&span);
+                                buffer.push(trace_id, span);
+                            }
+
+                            for trace in buffer.drain_all() {
+                                for span in trace {
+                                    if forward_span_tx.send(span).await.is_err() {
+                                        break;
+                                    }
+                                }
+                            }
+
+                            relay_control(&forward_control_tx, ReporterControl::Shutdown, ack).await;
+
+                            return;
+                        }
+                        None => return,
+                    }
+                }
+
+                span = span_rx.recv() => {
+                    let Some(span) = span else { return };
+
+                    let trace_id = trace_id_of(&span);
+                    let root = is_root(&span);
+
+                    buffer.push(trace_id, span);
+
+                    if !root {
+                        continue;
+                    }
+
+                    let trace = buffer.take(&trace_id);
+                    let Some(root_span) = trace.last() else {
+                        continue;
+                    };
+
+                    if !should_keep(&settings, root_span, &trace) {
+                        continue;
+                    }
+
+                    for span in trace {
+                        if forward_span_tx.send(span).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    });
+
+    (forward_span_rx, forward_control_rx)
+}
+
+/// Forwards a flush/shutdown request to the downstream reporter and waits for its ack,
+/// then acks the original caller. Returns `false` if the downstream reporter is gone.
+async fn relay_control(
+    forward_control_tx: &mpsc::UnboundedSender<ReporterControl>,
+    variant: fn(oneshot::Sender<()>) -> ReporterControl,
+    ack: oneshot::Sender<()>,
+) -> bool {
+    let (down_ack_tx, down_ack_rx) = oneshot::channel();
+
+    if forward_control_tx.send(variant(down_ack_tx)).is_err() {
+        let _ = ack.send(());
+        return false;
+    }
+
+    let _ = down_ack_rx.await;
+    let _ = ack.send(());
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_then_take_returns_items_in_push_order() {
+        let mut buffer = TraceBuffer::new(10);
+        let trace_id = (1, 1);
+
+        buffer.push(trace_id, "a");
+        buffer.push(trace_id, "b");
+
+        assert_eq!(buffer.take(&trace_id), vec!["a", "b"]);
+        assert!(buffer.buffered_trace_ids().is_empty());
+    }
+
+    #[test]
+    fn evicts_oldest_incomplete_trace_when_over_capacity() {
+        let mut buffer = TraceBuffer::new(2);
+
+        buffer.push((1, 1), "trace-1");
+        buffer.push((2, 2), "trace-2");
+        buffer.push((3, 3), "trace-3");
+
+        assert_eq!(buffer.buffered_trace_ids(), vec![(2, 2), (3, 3)]);
+        assert!(buffer.take(&(1, 1)).is_empty());
+        assert_eq!(buffer.take(&(2, 2)), vec!["trace-2"]);
+    }
+
+    #[test]
+    fn repeated_push_to_same_trace_does_not_count_twice_against_capacity() {
+        let mut buffer = TraceBuffer::new(1);
+        let trace_id = (1, 1);
+
+        buffer.push(trace_id, "a");
+        buffer.push(trace_id, "b");
+        buffer.push((2, 2), "c");
+
+        // trace_id (1, 1) is the oldest and should be evicted, not kept around because it
+        // happened to receive two pushes.
+        assert_eq!(buffer.buffered_trace_ids(), vec![(2, 2)]);
+    }
+
+    #[test]
+    fn drain_all_empties_the_buffer_and_returns_every_trace() {
+        let mut buffer = TraceBuffer::new(10);
+
+        buffer.push((1, 1), "a");
+        buffer.push((2, 2), "b");
+
+        let mut drained = buffer.drain_all();
+        drained.sort();
+
+        assert_eq!(drained, vec![vec!["a"], vec!["b"]]);
+        assert!(buffer.buffered_trace_ids().is_empty());
+    }
+}